@@ -15,8 +15,9 @@
 //! for more examples.
 
 use std::{
+    io,
     path::{Path, PathBuf},
-    sync::atomic::{AtomicU16, Ordering},
+    sync::{OnceLock, RwLock},
 };
 
 /// Macro for including a source file, and writing it to a new `AutoDeletePath::temp`.
@@ -54,7 +55,20 @@ macro_rules! include_to_auto_delete_path {
 ///
 /// Useful for creating temporary files that you want to be deleted automatically.
 pub struct AutoDeletePath {
-    path: PathBuf,
+    /// `None` once the path has been handed off via [AutoDeletePath::keep], so `Drop` knows to
+    /// skip deletion.
+    path: Option<PathBuf>,
+    kind: Kind,
+}
+
+/// What kind of filesystem object an [AutoDeletePath] owns, so `Drop` knows whether to call
+/// `remove_file` or `remove_dir_all` without having to ask the filesystem.
+enum Kind {
+    File,
+    Dir,
+    /// The path was only ever synthesized (e.g. via [AutoDeletePath::temp]), so nothing is known
+    /// about what, if anything, was created at it. Falls back to probing at drop time.
+    Unknown,
 }
 
 impl AutoDeletePath {
@@ -91,39 +105,329 @@ impl AutoDeletePath {
     /// ```
     pub fn temp() -> Self {
         Self {
-            path: create_temp_path(),
+            path: Some(create_temp_path()),
+            kind: Kind::Unknown,
         }
     }
+
+    /// Creates an AutoDeletePath in the given directory, instead of the default temp directory.
+    /// Like [AutoDeletePath::temp], this method just returns a path; you have to create the file
+    /// or folder yourself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let dir = std::env::temp_dir();
+    /// let temp_path = auto_delete_path::AutoDeletePath::temp_in(&dir);
+    /// assert!(temp_path.as_ref().starts_with(&dir));
+    /// ```
+    pub fn temp_in(dir: impl AsRef<Path>) -> Self {
+        Self {
+            path: Some(create_temp_path_at_directory(dir)),
+            kind: Kind::Unknown,
+        }
+    }
+
+    /// Atomically creates a new, empty file in the default temp directory and returns an
+    /// `AutoDeletePath` owning it. Unlike [AutoDeletePath::temp], the file is guaranteed to exist
+    /// by the time this returns, and `Drop` will always clean it up with `remove_file` rather
+    /// than probing the filesystem to guess.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_path = auto_delete_path::AutoDeletePath::new_file().unwrap();
+    /// assert!(temp_path.as_ref().is_file());
+    /// ```
+    pub fn new_file() -> io::Result<Self> {
+        Self::builder().build_file()
+    }
+
+    /// Atomically creates a new, empty directory in the default temp directory and returns an
+    /// `AutoDeletePath` owning it. Unlike [AutoDeletePath::temp], the directory is guaranteed to
+    /// exist by the time this returns, and `Drop` will always clean it up with `remove_dir_all`
+    /// rather than probing the filesystem to guess.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_path = auto_delete_path::AutoDeletePath::new_dir().unwrap();
+    /// assert!(temp_path.as_ref().is_dir());
+    /// ```
+    pub fn new_dir() -> io::Result<Self> {
+        Self::builder().build_dir()
+    }
+
+    /// Returns a [Builder] for constructing an `AutoDeletePath` with a custom prefix, suffix,
+    /// random component length, or parent directory, and with the backing file or directory
+    /// actually created (atomically, with collision retries) rather than merely named.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_path = auto_delete_path::AutoDeletePath::builder()
+    ///     .prefix("myapp-")
+    ///     .suffix(".txt")
+    ///     .build_file()
+    ///     .unwrap();
+    /// assert!(temp_path.as_ref().exists());
+    /// ```
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    /// Consumes the `AutoDeletePath`, suppressing its automatic deletion, and returns the owned
+    /// path.
+    ///
+    /// Useful for promoting a temp file or directory to a permanent artifact, e.g. keeping a test
+    /// failure's captured output around for inspection afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_path = auto_delete_path::AutoDeletePath::new_file().unwrap();
+    /// let path = temp_path.keep();
+    /// assert!(path.exists());
+    /// std::fs::remove_file(path).unwrap();
+    /// ```
+    pub fn keep(mut self) -> PathBuf {
+        self.path.take().expect("path already taken")
+    }
+
+    /// Consumes the `AutoDeletePath`, eagerly deleting the underlying file or directory and
+    /// surfacing any error, rather than silently swallowing it as `Drop` does.
+    ///
+    /// Useful for tests that need to assert the temp area was actually cleaned up, or for any
+    /// caller that wants to react to a failed cleanup (permission denied, a busy network mount).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_path = auto_delete_path::AutoDeletePath::new_file().unwrap();
+    /// let path = temp_path.as_ref().to_owned();
+    /// temp_path.close().unwrap();
+    /// assert!(!path.exists());
+    /// ```
+    pub fn close(mut self) -> io::Result<()> {
+        let path = self.path.take().expect("path already taken");
+        delete(&path, &self.kind)
+    }
 }
 
 impl std::convert::AsRef<Path> for AutoDeletePath {
     fn as_ref(&self) -> &Path {
-        &self.path
+        self.path.as_deref().expect("path already taken")
     }
 }
 
 impl Drop for AutoDeletePath {
     fn drop(&mut self) {
-        if self.path.is_dir() {
-            std::fs::remove_dir_all(&self.path).ok();
-        } else {
-            std::fs::remove_file(&self.path).ok();
+        let Some(path) = self.path.take() else {
+            return;
+        };
+        delete(&path, &self.kind).ok();
+    }
+}
+
+/// Deletes `path` according to `kind`: `remove_file` for a [Kind::File], `remove_dir_all` for a
+/// [Kind::Dir], and a best-effort probe to pick one for [Kind::Unknown].
+fn delete(path: &Path, kind: &Kind) -> io::Result<()> {
+    match kind {
+        Kind::File => std::fs::remove_file(path),
+        Kind::Dir => std::fs::remove_dir_all(path),
+        Kind::Unknown => {
+            if path.is_dir() {
+                std::fs::remove_dir_all(path)
+            } else {
+                std::fs::remove_file(path)
+            }
         }
     }
 }
 
-static PATH_COUNT: AtomicU16 = AtomicU16::new(1);
+/// Builder for an [AutoDeletePath], letting you customize the prefix, suffix, and random
+/// component length of the generated name, as well as the directory it's created in.
+///
+/// Construct one with [AutoDeletePath::builder].
+///
+/// There's no kind-agnostic `build()`: the backing object has to be created atomically up front
+/// (see [Builder::build_file] and [Builder::build_dir]) so `Drop` can delete it deterministically,
+/// which means the builder has to know whether it's making a file or a directory before it can do
+/// anything. [AutoDeletePath::new_file]/[AutoDeletePath::new_dir] are the equivalent shortcuts when
+/// you don't need a custom prefix/suffix/directory.
+pub struct Builder {
+    prefix: String,
+    suffix: String,
+    rand_bytes: usize,
+    dir: PathBuf,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Self {
+            prefix: String::new(),
+            suffix: String::new(),
+            rand_bytes: 12,
+            dir: temp_dir(),
+        }
+    }
+
+    /// Sets the prefix prepended to the random component of the generated name.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_owned();
+        self
+    }
+
+    /// Sets the suffix appended to the random component of the generated name.
+    pub fn suffix(mut self, suffix: &str) -> Self {
+        self.suffix = suffix.to_owned();
+        self
+    }
+
+    /// Sets how many random alphanumeric characters make up the generated name. Defaults to 12.
+    pub fn rand_bytes(mut self, rand_bytes: usize) -> Self {
+        self.rand_bytes = rand_bytes;
+        self
+    }
+
+    /// Sets the directory the path will be created in. Defaults to the directory set by
+    /// [override_temp_dir], or `std::env::temp_dir()` if none is set.
+    pub fn in_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.dir = dir.as_ref().to_owned();
+        self
+    }
+
+    /// Atomically creates a new, empty file with the configured name, retrying with a freshly
+    /// generated random component on collision.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_path = auto_delete_path::AutoDeletePath::builder().build_file().unwrap();
+    /// assert!(temp_path.as_ref().is_file());
+    /// ```
+    pub fn build_file(self) -> io::Result<AutoDeletePath> {
+        let path = self.create(|candidate| {
+            std::fs::OpenOptions::new()
+                .create_new(true)
+                .write(true)
+                .open(candidate)
+                .map(|_| ())
+        })?;
+        Ok(AutoDeletePath {
+            path: Some(path),
+            kind: Kind::File,
+        })
+    }
+
+    /// Atomically creates a new, empty directory with the configured name, retrying with a
+    /// freshly generated random component on collision.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_path = auto_delete_path::AutoDeletePath::builder().build_dir().unwrap();
+    /// assert!(temp_path.as_ref().is_dir());
+    /// ```
+    pub fn build_dir(self) -> io::Result<AutoDeletePath> {
+        let path = self.create(|candidate| std::fs::create_dir(candidate))?;
+        Ok(AutoDeletePath {
+            path: Some(path),
+            kind: Kind::Dir,
+        })
+    }
+
+    fn create(&self, try_create: impl Fn(&Path) -> io::Result<()>) -> io::Result<PathBuf> {
+        // A process could plausibly lose every race for a while under heavy concurrent load, but
+        // never indefinitely, so retry a very large, but bounded, number of times.
+        const MAX_ATTEMPTS: u32 = 1 << 31;
+        for _ in 0..MAX_ATTEMPTS {
+            let candidate = self.dir.join(format!(
+                "{}{}{}",
+                self.prefix,
+                random_string(self.rand_bytes)?,
+                self.suffix
+            ));
+            match try_create(&candidate) {
+                Ok(()) => return Ok(candidate),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "could not find an unused temp path after exhausting all attempts",
+        ))
+    }
+}
 
 /// Creates a random path at the default temp directory (usually /tmp).
+///
+/// # Panics
+///
+/// Panics if the OS CSPRNG can't be read. `temp()`/`temp_in()` return a bare `PathBuf`/`Self`
+/// rather than a `Result`, so there's nowhere to surface that failure except a panic; the
+/// fallible constructors (`new_file`, `new_dir`, `Builder::build_file`, `Builder::build_dir`)
+/// propagate it as an `io::Error` instead.
 fn create_temp_path() -> PathBuf {
-    create_temp_path_at_directory(std::env::temp_dir())
+    create_temp_path_at_directory(temp_dir())
 }
 
 /// Creates a random path at the specified directory.
+///
+/// # Panics
+///
+/// See [create_temp_path].
 fn create_temp_path_at_directory<P: AsRef<Path>>(directory: P) -> PathBuf {
-    PathBuf::from(format!(
-        "{}/rustytemp-{}",
-        directory.as_ref().display(),
-        PATH_COUNT.fetch_add(1, Ordering::Relaxed)
+    directory.as_ref().join(format!(
+        "rustytemp-{}",
+        random_string(12).expect("failed to read OS randomness")
     ))
 }
+
+static OVERRIDE_TEMP_DIR: OnceLock<RwLock<Option<PathBuf>>> = OnceLock::new();
+
+/// Overrides the directory used in place of `std::env::temp_dir()` by [AutoDeletePath::temp],
+/// [AutoDeletePath::new_file], [AutoDeletePath::new_dir], and [AutoDeletePath::builder], for the
+/// remainder of the process.
+///
+/// Useful when the OS temp directory is unsuitable: sandboxes, tmpfs size limits, or tests that
+/// must keep their temp files on a specific volume.
+///
+/// # Examples
+///
+/// ```
+/// let dir = auto_delete_path::AutoDeletePath::new_dir().unwrap();
+/// auto_delete_path::override_temp_dir(dir.as_ref().to_owned());
+/// let temp_path = auto_delete_path::AutoDeletePath::temp();
+/// assert!(temp_path.as_ref().starts_with(&dir));
+/// ```
+pub fn override_temp_dir(dir: PathBuf) {
+    *OVERRIDE_TEMP_DIR
+        .get_or_init(|| RwLock::new(None))
+        .write()
+        .unwrap() = Some(dir);
+}
+
+/// Returns the directory that `temp()` and friends currently create their paths in: the override
+/// set by [override_temp_dir], if any, or `std::env::temp_dir()` otherwise.
+fn temp_dir() -> PathBuf {
+    OVERRIDE_TEMP_DIR
+        .get()
+        .and_then(|lock| lock.read().unwrap().clone())
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Generates `len` random alphanumeric characters, drawing fresh bytes from the OS CSPRNG
+/// (via `getrandom`) on every call.
+fn random_string(len: usize) -> io::Result<String> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+    let mut bytes = vec![0u8; len];
+    getrandom::getrandom(&mut bytes)?;
+
+    Ok(bytes
+        .into_iter()
+        .map(|b| ALPHABET[b as usize % ALPHABET.len()] as char)
+        .collect())
+}